@@ -0,0 +1,57 @@
+// Copyright Valkey GLIDE Project Contributors - SPDX Identifier: Apache-2.0
+
+//! Benchmarks concurrent `get_script_async` throughput against the sharded container,
+//! demonstrating that lookups landing in different shards don't serialize against
+//! each other the way a single global lock would.
+//!
+//! Run with `cargo bench -p glide-core --bench scripts_container_throughput`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use glide_core::scripts_container::{add_script_async, get_script_async};
+use std::sync::Arc;
+
+/// Registers `count` distinct scripts and returns their hashes, so a benchmark can look
+/// up a spread of hashes rather than hammering a single shard.
+async fn seed_scripts(count: usize) -> Vec<String> {
+    let mut hashes = Vec::with_capacity(count);
+    for i in 0..count {
+        hashes.push(add_script_async(format!("return {i}").as_bytes()).await);
+    }
+    hashes
+}
+
+fn bench_concurrent_reads(c: &mut Criterion) {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let hashes = Arc::new(rt.block_on(seed_scripts(256)));
+
+    let mut group = c.benchmark_group("scripts_container_concurrent_get");
+    for reader_tasks in [1, 4, 8, 16] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(reader_tasks),
+            &reader_tasks,
+            |b, &reader_tasks| {
+                b.iter(|| {
+                    rt.block_on(async {
+                        let mut tasks = Vec::with_capacity(reader_tasks);
+                        for t in 0..reader_tasks {
+                            let hashes = Arc::clone(&hashes);
+                            tasks.push(tokio::spawn(async move {
+                                for i in 0..1000 {
+                                    let hash = &hashes[(t + i) % hashes.len()];
+                                    assert!(get_script_async(hash).await.is_some());
+                                }
+                            }));
+                        }
+                        for task in tasks {
+                            task.await.unwrap();
+                        }
+                    });
+                });
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_concurrent_reads);
+criterion_main!(benches);
@@ -0,0 +1,221 @@
+// Distributed lock (Redlock), built on the dedicated connection pool.
+//
+// Single-shard acquisition is `SET resource token NX PX ttl_ms`. Release and extend run
+// Lua scripts that only act when the stored token still matches this lock's token, so a
+// holder can never release or extend a lock that someone else acquired after this one
+// expired. `acquire_lock_quorum` implements the Redlock algorithm across N independent
+// primaries: the lock is held only if a majority acknowledged the SET within the TTL,
+// minus a clock-drift allowance.
+
+use crate::connection_pool::ConnectionHandle;
+use futures::future::join_all;
+use nanoid::nanoid;
+use redis::{cmd, RedisResult, Script};
+use std::time::{Duration, Instant};
+
+const RELEASE_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("DEL", KEYS[1])
+else
+    return 0
+end
+"#;
+
+const EXTEND_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("PEXPIRE", KEYS[1], ARGV[2])
+else
+    return 0
+end
+"#;
+
+/// Fraction of the TTL reserved as a clock-drift allowance between the primaries
+/// participating in a quorum lock, per the Redlock algorithm.
+const CLOCK_DRIFT_FACTOR: f64 = 0.01;
+
+/// Default time a single per-node SET is allowed to take during quorum acquisition,
+/// so one dead node can't stall the whole attempt.
+const DEFAULT_NODE_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// A held distributed lock on `resource`. The lock on the server will expire on its own
+/// once its TTL elapses, but `LockGuard` also owns a dedicated `ConnectionHandle` (and,
+/// transitively, a semaphore permit per node it's held on) that is only returned to the
+/// pool by `release()` - dropping a `LockGuard` without calling `release()` leaks that
+/// handle. Always call `release()` once you're done with the lock.
+#[must_use = "dropping a LockGuard leaks its dedicated connection handle; call `release()`"]
+pub struct LockGuard<'a> {
+    client: &'a Client,
+    handle: ConnectionHandle,
+    resource: String,
+    token: String,
+    // The primaries this lock is held on; for a single-shard lock, exactly one.
+    node_ids: Vec<String>,
+}
+
+impl<'a> LockGuard<'a> {
+    /// Releases the lock on every node it was acquired against and returns the
+    /// underlying dedicated connection handle to the pool. A node that can't be reached
+    /// is skipped rather than aborting the release - exactly the kind of partial
+    /// failure Redlock is meant to tolerate - and the handle is always returned to the
+    /// pool regardless of how many nodes responded.
+    pub async fn release(self) -> RedisResult<()> {
+        let script = Script::new(RELEASE_SCRIPT);
+        for node_id in &self.node_ids {
+            let Ok(mut conn) = self
+                .client
+                .connection_pool
+                .get_connection(self.handle, node_id)
+                .await
+            else {
+                continue;
+            };
+            let _: RedisResult<i64> = script
+                .key(&self.resource)
+                .arg(&self.token)
+                .invoke_async(&mut conn)
+                .await;
+        }
+        self.client.connection_pool.release_dedicated(self.handle).await;
+        Ok(())
+    }
+
+    /// Extends the lock's TTL on every node that still holds it for this token. Returns
+    /// whether a majority of nodes acknowledged the extension. A node that can't be
+    /// reached just doesn't count toward quorum, the same as during acquisition -
+    /// a minority of down nodes must never stop the rest from extending.
+    pub async fn extend(&self, ttl: Duration) -> RedisResult<bool> {
+        let script = Script::new(EXTEND_SCRIPT);
+        let mut acked = 0usize;
+        for node_id in &self.node_ids {
+            let Ok(mut conn) = self
+                .client
+                .connection_pool
+                .get_connection(self.handle, node_id)
+                .await
+            else {
+                continue;
+            };
+            let result: RedisResult<i64> = script
+                .key(&self.resource)
+                .arg(&self.token)
+                .arg(ttl.as_millis() as i64)
+                .invoke_async(&mut conn)
+                .await;
+            if matches!(result, Ok(1)) {
+                acked += 1;
+            }
+        }
+        Ok(acked * 2 > self.node_ids.len())
+    }
+}
+
+impl Client {
+    /// Acquires a lock on `resource` against a single shard's primary. Returns `None`
+    /// if the resource is already locked.
+    pub async fn acquire_lock(
+        &self,
+        shard_id: &str,
+        resource: &str,
+        ttl: Duration,
+    ) -> RedisResult<Option<LockGuard<'_>>> {
+        let node_id = self.connection_pool.find_primary(shard_id).await?;
+        self.acquire_lock_on_nodes(vec![node_id], resource, ttl, DEFAULT_NODE_TIMEOUT)
+            .await
+    }
+
+    /// Acquires a Redlock-style quorum lock on `resource` across the given primaries.
+    /// The lock is considered held only if a majority (N/2 + 1) acknowledged the SET
+    /// and the elapsed wall-clock time is still within `ttl` minus a clock-drift
+    /// allowance; on failure, the release script is issued to every node so no partial
+    /// acquisition lingers.
+    pub async fn acquire_lock_quorum(
+        &self,
+        node_ids: &[String],
+        resource: &str,
+        ttl: Duration,
+        node_timeout: Duration,
+    ) -> RedisResult<Option<LockGuard<'_>>> {
+        self.acquire_lock_on_nodes(node_ids.to_vec(), resource, ttl, node_timeout)
+            .await
+    }
+
+    async fn acquire_lock_on_nodes(
+        &self,
+        node_ids: Vec<String>,
+        resource: &str,
+        ttl: Duration,
+        node_timeout: Duration,
+    ) -> RedisResult<Option<LockGuard<'_>>> {
+        let handle = self.connection_pool.acquire_dedicated().await;
+        let token = nanoid!();
+        let start = Instant::now();
+
+        // Fire the per-node SET attempts concurrently - the whole point of the
+        // per-node timeout is so one dead or slow node can't stall the attempt, but a
+        // sequential loop defeats that by making the total elapsed time scale with N
+        // regardless of how many nodes are actually slow.
+        let attempts = node_ids.iter().map(|node_id| {
+            let token = &token;
+            async move {
+                let attempt = async {
+                    let mut conn = self.connection_pool.get_connection(handle, node_id).await?;
+                    let mut set_cmd = cmd("SET");
+                    set_cmd
+                        .arg(resource)
+                        .arg(token)
+                        .arg("NX")
+                        .arg("PX")
+                        .arg(ttl.as_millis() as i64);
+                    set_cmd.query_async::<Option<String>>(&mut conn).await
+                };
+                let acquired = matches!(
+                    tokio::time::timeout(node_timeout, attempt).await,
+                    Ok(Ok(Some(_)))
+                );
+                (node_id, acquired)
+            }
+        });
+        let acked: Vec<String> = join_all(attempts)
+            .await
+            .into_iter()
+            .filter(|(_, acquired)| *acquired)
+            .map(|(node_id, _)| node_id.clone())
+            .collect();
+
+        let elapsed = start.elapsed();
+
+        if has_quorum(acked.len(), node_ids.len(), elapsed, ttl) {
+            Ok(Some(LockGuard {
+                client: self,
+                handle,
+                resource: resource.to_string(),
+                token,
+                node_ids: acked,
+            }))
+        } else {
+            // Didn't reach quorum in time: release wherever we did acquire. The
+            // release script is a no-op on nodes we never acquired, so it's safe to
+            // issue it to all of them.
+            let abandoned = LockGuard {
+                client: self,
+                handle,
+                resource: resource.to_string(),
+                token,
+                node_ids,
+            };
+            let _ = abandoned.release().await;
+            Ok(None)
+        }
+    }
+}
+
+/// Whether `acked` (the number of primaries that acknowledged the SET) out of `total`
+/// participating nodes satisfies Redlock: a strict majority, acquired within `ttl` minus
+/// a clock-drift allowance. Split out from `acquire_lock_on_nodes` so the quorum/drift
+/// arithmetic can be tested without a live connection pool.
+pub(crate) fn has_quorum(acked: usize, total: usize, elapsed: Duration, ttl: Duration) -> bool {
+    let drift = Duration::from_secs_f64(ttl.as_secs_f64() * CLOCK_DRIFT_FACTOR);
+    let within_ttl = ttl.checked_sub(drift).is_some_and(|budget| elapsed < budget);
+    let quorum = total / 2 + 1;
+    acked >= quorum && within_ttl
+}
@@ -0,0 +1,24 @@
+use crate::client::distributed_lock::has_quorum;
+use std::time::Duration;
+
+#[test]
+fn test_quorum_requires_strict_majority() {
+    // 5 nodes: 2 acks is not a majority, 3 is.
+    assert!(!has_quorum(2, 5, Duration::from_millis(1), Duration::from_secs(10)));
+    assert!(has_quorum(3, 5, Duration::from_millis(1), Duration::from_secs(10)));
+}
+
+#[test]
+fn test_quorum_on_single_node_lock() {
+    assert!(has_quorum(1, 1, Duration::from_millis(1), Duration::from_secs(10)));
+    assert!(!has_quorum(0, 1, Duration::from_millis(1), Duration::from_secs(10)));
+}
+
+#[test]
+fn test_quorum_fails_once_clock_drift_budget_is_exceeded() {
+    let ttl = Duration::from_millis(1000);
+    // Comfortably within the TTL minus the 1% drift allowance.
+    assert!(has_quorum(3, 5, Duration::from_millis(500), ttl));
+    // Past `ttl - drift`, even a unanimous quorum can't be trusted to still be held.
+    assert!(!has_quorum(5, 5, Duration::from_millis(999), ttl));
+}
@@ -0,0 +1,66 @@
+// Pub/Sub on dedicated connection handles.
+//
+// A connection that's issued SUBSCRIBE/PSUBSCRIBE can no longer serve ordinary
+// request/response commands, so subscriptions always go through a dedicated handle:
+// `ConnectionPool::get_pubsub` converts that handle's connection to a given node into
+// push-message mode and hands back a channel of incoming `Msg`s, and `release_dedicated`
+// tears the connection down instead of returning it to the shared pool.
+
+use crate::connection_pool::ConnectionHandle;
+use redis::{cmd, Msg, RedisResult};
+use tokio::sync::mpsc;
+
+impl Client {
+    /// Subscribes `handle`'s connection to `node_id` to `channels` and returns a
+    /// stream of incoming messages. The handle should not be used for ordinary
+    /// commands on `node_id` afterward - if the connection drops (failover, topology
+    /// change), the returned receiver closes and the caller should resubscribe.
+    pub async fn subscribe(
+        &self,
+        handle: ConnectionHandle,
+        node_id: &str,
+        channels: &[String],
+    ) -> RedisResult<mpsc::Receiver<Msg>> {
+        let rx = self.connection_pool.get_pubsub(handle, node_id).await?;
+        let mut conn = self.connection_pool.get_connection(handle, node_id).await?;
+        let mut subscribe_cmd = cmd("SUBSCRIBE");
+        for channel in channels {
+            subscribe_cmd.arg(channel);
+        }
+        subscribe_cmd.query_async::<()>(&mut conn).await?;
+        Ok(rx)
+    }
+
+    /// Same as [`Self::subscribe`], but for glob `patterns` (PSUBSCRIBE).
+    pub async fn psubscribe(
+        &self,
+        handle: ConnectionHandle,
+        node_id: &str,
+        patterns: &[String],
+    ) -> RedisResult<mpsc::Receiver<Msg>> {
+        let rx = self.connection_pool.get_pubsub(handle, node_id).await?;
+        let mut conn = self.connection_pool.get_connection(handle, node_id).await?;
+        let mut psubscribe_cmd = cmd("PSUBSCRIBE");
+        for pattern in patterns {
+            psubscribe_cmd.arg(pattern);
+        }
+        psubscribe_cmd.query_async::<()>(&mut conn).await?;
+        Ok(rx)
+    }
+
+    /// Unsubscribes `handle`'s connection to `node_id` from `channels` (or from all
+    /// channels, if `channels` is empty).
+    pub async fn unsubscribe(
+        &self,
+        handle: ConnectionHandle,
+        node_id: &str,
+        channels: &[String],
+    ) -> RedisResult<()> {
+        let mut conn = self.connection_pool.get_connection(handle, node_id).await?;
+        let mut unsubscribe_cmd = cmd("UNSUBSCRIBE");
+        for channel in channels {
+            unsubscribe_cmd.arg(channel);
+        }
+        unsubscribe_cmd.query_async::<()>(&mut conn).await
+    }
+}
@@ -0,0 +1,135 @@
+// Real WATCH/MULTI/EXEC transaction support, built on dedicated connection handles.
+//
+// A transaction only gets WATCH's optimistic-locking guarantee if every command in it
+// (WATCH, the reads that decide what to write, MULTI, the queued writes, and EXEC) runs
+// on the same underlying connection. `Client::transaction_cas` pins all of that to one
+// `ConnectionHandle` and retries automatically when EXEC reports the watch was
+// invalidated by a concurrent writer.
+
+use crate::connection_pool::ConnectionHandle;
+use redis::{cmd, Cmd, ErrorKind, RedisError, RedisResult, Value};
+use std::future::Future;
+use std::time::Duration;
+
+/// Tunables for [`Client::transaction_cas`]'s retry loop.
+#[derive(Clone, Copy, Debug)]
+pub struct TransactionRetryConfig {
+    /// Maximum number of attempts, including the first, before giving up.
+    pub max_attempts: u32,
+    /// Base delay between attempts; attempt `n` waits `base_backoff * n`.
+    pub base_backoff: Duration,
+}
+
+impl Default for TransactionRetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_backoff: Duration::from_millis(20),
+        }
+    }
+}
+
+/// The queued writes a `transaction_cas` closure wants executed inside MULTI/EXEC.
+pub struct QueuedCommands(pub Vec<Cmd>);
+
+impl Client {
+    /// Runs `body` as a compare-and-swap transaction against `keys`: WATCHes the keys,
+    /// lets `body` read their current values over the same handle and decide what to
+    /// write, then wraps the result in MULTI/EXEC. If EXEC reports the watch was
+    /// invalidated (a nil array), the attempt is UNWATCHed, the handle is released, and
+    /// the whole thing is retried on a freshly-acquired handle, for at most
+    /// `config.max_attempts` attempts in all. The handle is released on every return path.
+    pub async fn transaction_cas<F, Fut>(
+        &self,
+        keys: &[String],
+        config: TransactionRetryConfig,
+        mut body: F,
+    ) -> RedisResult<Value>
+    where
+        F: FnMut(ConnectionHandle) -> Fut,
+        Fut: Future<Output = RedisResult<QueuedCommands>>,
+    {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let handle = self.connection_pool.acquire_dedicated().await;
+            let result = self.run_cas_attempt(handle, keys, &mut body).await;
+            self.connection_pool.release_dedicated(handle).await;
+
+            match result {
+                Ok(Some(value)) => return Ok(value),
+                Ok(None) if should_retry(attempt, &config) => {
+                    tokio::time::sleep(config.base_backoff * attempt).await;
+                }
+                Ok(None) => {
+                    return Err(RedisError::from((
+                        ErrorKind::ClientError,
+                        "Transaction exceeded max CAS retries",
+                    )))
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Runs a single WATCH/MULTI/EXEC attempt on `handle`. Returns `Ok(None)` when EXEC
+    /// reports the watch was invalidated, so the caller knows to retry.
+    async fn run_cas_attempt<F, Fut>(
+        &self,
+        handle: ConnectionHandle,
+        keys: &[String],
+        body: &mut F,
+    ) -> RedisResult<Option<Value>>
+    where
+        F: FnMut(ConnectionHandle) -> Fut,
+        Fut: Future<Output = RedisResult<QueuedCommands>>,
+    {
+        let mut watch_cmd = cmd("WATCH");
+        for key in keys {
+            watch_cmd.arg(key);
+        }
+        self.send_command_dedicated(&watch_cmd, handle).await?;
+
+        let queued = match body(handle).await {
+            Ok(queued) => queued,
+            Err(e) => {
+                let _ = self.send_command_dedicated(&cmd("UNWATCH"), handle).await;
+                return Err(e);
+            }
+        };
+
+        if let Err(e) = self.send_command_dedicated(&cmd("MULTI"), handle).await {
+            let _ = self.send_command_dedicated(&cmd("UNWATCH"), handle).await;
+            return Err(e);
+        }
+        for queued_cmd in &queued.0 {
+            if let Err(e) = self.send_command_dedicated(queued_cmd, handle).await {
+                // The connection is already in server-side queueing mode from the
+                // successful MULTI above, so UNWATCH here would itself just get queued
+                // and do nothing. DISCARD exits the transaction (and clears the WATCH)
+                // so the connection is clean before it goes back to the shared pool.
+                let _ = self.send_command_dedicated(&cmd("DISCARD"), handle).await;
+                return Err(e);
+            }
+        }
+
+        match self.send_command_dedicated(&cmd("EXEC"), handle).await {
+            Ok(Value::Nil) => {
+                let _ = self.send_command_dedicated(&cmd("UNWATCH"), handle).await;
+                Ok(None)
+            }
+            Ok(value) => Ok(Some(value)),
+            Err(e) => {
+                let _ = self.send_command_dedicated(&cmd("UNWATCH"), handle).await;
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Whether `transaction_cas`'s retry loop should make another attempt after a CAS
+/// failure on `attempt` (1-indexed). Split out so the retry-count arithmetic can be
+/// tested without a live connection pool.
+pub(crate) fn should_retry(attempt: u32, config: &TransactionRetryConfig) -> bool {
+    attempt < config.max_attempts
+}
@@ -0,0 +1,20 @@
+use crate::client::transaction::{should_retry, TransactionRetryConfig};
+
+#[test]
+fn test_retries_while_attempts_remain() {
+    let config = TransactionRetryConfig {
+        max_attempts: 3,
+        ..TransactionRetryConfig::default()
+    };
+    assert!(should_retry(1, &config));
+    assert!(should_retry(2, &config));
+}
+
+#[test]
+fn test_stops_retrying_once_max_attempts_reached() {
+    let config = TransactionRetryConfig {
+        max_attempts: 3,
+        ..TransactionRetryConfig::default()
+    };
+    assert!(!should_retry(3, &config));
+}
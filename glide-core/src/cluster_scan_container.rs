@@ -3,17 +3,94 @@
 use logger_core::log_debug;
 use nanoid::nanoid;
 use once_cell::sync::Lazy;
-use redis::{RedisResult, ScanStateRC};
+use redis::{ErrorKind, RedisError, RedisResult, ScanStateRC};
 use std::collections::HashMap;
-use tokio::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, RwLock};
 
-static CONTAINER: Lazy<Mutex<HashMap<String, ScanStateRC>>> =
+/// Tunables for the cluster scan cursor container's TTL/capacity eviction.
+#[derive(Clone, Copy, Debug)]
+pub struct ScanCursorContainerConfig {
+    /// A cursor untouched for longer than this is treated as expired.
+    pub ttl: Duration,
+    /// Maximum number of live cursors; once exceeded, the least-recently-accessed
+    /// cursor is evicted to make room for the new one. The cursor being inserted is
+    /// always kept, so `Some(0)` behaves like `Some(1)`. `None` means unbounded.
+    pub max_entries: Option<usize>,
+}
+
+impl Default for ScanCursorContainerConfig {
+    fn default() -> Self {
+        Self {
+            ttl: Duration::from_secs(10 * 60),
+            max_entries: None,
+        }
+    }
+}
+
+struct CursorEntry {
+    state: ScanStateRC,
+    last_access: Instant,
+}
+
+static CONTAINER: Lazy<Mutex<HashMap<String, CursorEntry>>> =
     Lazy::new(|| Mutex::new(HashMap::new()));
 
+static CONFIG: Lazy<RwLock<ScanCursorContainerConfig>> =
+    Lazy::new(|| RwLock::new(ScanCursorContainerConfig::default()));
+
+/// Override the default TTL/capacity for the cluster scan cursor container. Bindings
+/// that never finish a SCAN (dropped client, crashed process, aborted iteration) would
+/// otherwise leak a `ScanStateRC` forever; this bounds how long that can happen.
+pub async fn configure(config: ScanCursorContainerConfig) {
+    *CONFIG.write().await = config;
+}
+
+fn evict_expired(container: &mut HashMap<String, CursorEntry>, ttl: Duration) {
+    container.retain(|_, entry| entry.last_access.elapsed() < ttl);
+}
+
+/// Evicts the single least-recently-accessed cursor, if any are present. Returns
+/// whether an entry was actually removed, so a caller looping on container length
+/// can tell an empty container apart from one still over capacity.
+fn evict_oldest(container: &mut HashMap<String, CursorEntry>) -> bool {
+    if let Some(oldest_id) = container
+        .iter()
+        .min_by_key(|(_, entry)| entry.last_access)
+        .map(|(id, _)| id.clone())
+    {
+        container.remove(&oldest_id);
+        log_debug(
+            "scan_state_cursor evict",
+            format!("Evicted oldest scan_state_cursor with id: `{:?}`", oldest_id),
+        );
+        true
+    } else {
+        false
+    }
+}
+
 // Internal async implementation - use this from async contexts
 pub async fn insert_cluster_scan_cursor_async(scan_state: ScanStateRC) -> String {
     let id = nanoid!();
-    CONTAINER.lock().await.insert(id.clone(), scan_state);
+    let config = *CONFIG.read().await;
+    let mut container = CONTAINER.lock().await;
+
+    evict_expired(&mut container, config.ttl);
+    if let Some(max_entries) = config.max_entries {
+        // `max_entries == 0` would otherwise spin forever: the container is already
+        // empty, so `len() >= 0` stays true but `evict_oldest` has nothing left to
+        // remove. Stop as soon as an eviction pass comes up empty.
+        while container.len() >= max_entries && evict_oldest(&mut container) {}
+    }
+
+    container.insert(
+        id.clone(),
+        CursorEntry {
+            state: scan_state,
+            last_access: Instant::now(),
+        },
+    );
     log_debug(
         "scan_state_cursor insert",
         format!("Inserted to container scan_state_cursor with id: `{:?}`", id),
@@ -28,17 +105,37 @@ pub fn insert_cluster_scan_cursor(scan_state: ScanStateRC) -> String {
 
 // Internal async implementation - use this from async contexts
 pub async fn get_cluster_scan_cursor_async(id: String) -> RedisResult<ScanStateRC> {
-    let scan_state_rc = CONTAINER.lock().await.get(&id).cloned();
-    log_debug(
-        "scan_state_cursor get",
-        format!("Retrieved from container scan_state_cursor with id: `{:?}`", id),
-    );
-    scan_state_rc.ok_or_else(|| {
-        redis::RedisError::from((
-            redis::ErrorKind::ResponseError,
+    let ttl = CONFIG.read().await.ttl;
+    let mut container = CONTAINER.lock().await;
+
+    match container.get_mut(&id) {
+        Some(entry) if entry.last_access.elapsed() < ttl => {
+            entry.last_access = Instant::now();
+            let scan_state_rc = entry.state.clone();
+            log_debug(
+                "scan_state_cursor get",
+                format!("Retrieved from container scan_state_cursor with id: `{:?}`", id),
+            );
+            Ok(scan_state_rc)
+        }
+        Some(_) => {
+            // Distinct from "invalid cursor" so bindings can surface a retryable
+            // condition rather than treat the cursor as malformed.
+            container.remove(&id);
+            log_debug(
+                "scan_state_cursor get",
+                format!("scan_state_cursor with id: `{:?}` expired", id),
+            );
+            Err(RedisError::from((
+                ErrorKind::ResponseError,
+                "Scan cursor expired",
+            )))
+        }
+        None => Err(RedisError::from((
+            ErrorKind::ResponseError,
             "Invalid scan state cursor",
-        ))
-    })
+        ))),
+    }
 }
 
 // Public blocking API - use this from sync contexts only
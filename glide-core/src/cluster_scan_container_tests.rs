@@ -0,0 +1,73 @@
+use crate::cluster_scan_container::{
+    configure, get_cluster_scan_cursor_async, insert_cluster_scan_cursor_async,
+    ScanCursorContainerConfig,
+};
+use once_cell::sync::Lazy;
+use redis::ScanStateRC;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+/// `configure()` mutates process-wide state shared by every test in this binary, and
+/// the default test runner executes tests concurrently on separate threads. Every test
+/// here calls `configure()`, so each takes this guard for its whole run to avoid
+/// stomping another test's config mid-sleep; there's no `serial_test` (or similar)
+/// dependency in this repo to reach for instead.
+static CONFIG_TEST_GUARD: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+fn dummy_state() -> ScanStateRC {
+    ScanStateRC::default()
+}
+
+#[tokio::test]
+async fn test_expired_cursor_is_evicted_on_access() {
+    let _guard = CONFIG_TEST_GUARD.lock().await;
+    configure(ScanCursorContainerConfig {
+        ttl: Duration::from_millis(20),
+        max_entries: None,
+    })
+    .await;
+
+    let id = insert_cluster_scan_cursor_async(dummy_state()).await;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let err = get_cluster_scan_cursor_async(id).await.unwrap_err();
+    assert!(err.to_string().to_lowercase().contains("expired"));
+
+    configure(ScanCursorContainerConfig::default()).await;
+}
+
+#[tokio::test]
+async fn test_max_entries_evicts_the_oldest_cursor() {
+    let _guard = CONFIG_TEST_GUARD.lock().await;
+    configure(ScanCursorContainerConfig {
+        ttl: Duration::from_secs(600),
+        max_entries: Some(1),
+    })
+    .await;
+
+    let first = insert_cluster_scan_cursor_async(dummy_state()).await;
+    let second = insert_cluster_scan_cursor_async(dummy_state()).await;
+
+    // Capacity of 1: inserting `second` must have evicted `first`.
+    assert!(get_cluster_scan_cursor_async(first).await.is_err());
+    assert!(get_cluster_scan_cursor_async(second).await.is_ok());
+
+    configure(ScanCursorContainerConfig::default()).await;
+}
+
+#[tokio::test]
+async fn test_max_entries_zero_does_not_hang() {
+    let _guard = CONFIG_TEST_GUARD.lock().await;
+    configure(ScanCursorContainerConfig {
+        ttl: Duration::from_secs(600),
+        max_entries: Some(0),
+    })
+    .await;
+
+    // Regression test: this used to spin forever trying to evict down to an
+    // already-empty container. Completing at all is the assertion.
+    let id = insert_cluster_scan_cursor_async(dummy_state()).await;
+    assert!(get_cluster_scan_cursor_async(id).await.is_ok());
+
+    configure(ScanCursorContainerConfig::default()).await;
+}
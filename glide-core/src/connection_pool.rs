@@ -1,7 +1,58 @@
-use redis::{aio::MultiplexedConnection, Client as RedisClient, RedisResult, RedisError, ErrorKind};
+// Copyright Valkey GLIDE Project Contributors - SPDX Identifier: Apache-2.0
+
+use redis::{
+    aio::MultiplexedConnection, cmd, Client as RedisClient, ErrorKind, Msg, RedisError,
+    RedisResult,
+};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, RwLock, Semaphore};
+
+/// Whether a registered node is currently serving as a shard's primary or one of its replicas.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeRole {
+    Primary,
+    Replica,
+}
+
+/// A registered node: the client used to open connections to it, its current role, the
+/// shard (primary + replicas) it belongs to, and the credentials to reapply on every
+/// freshly-opened connection.
+struct NodeInfo {
+    client: RedisClient,
+    role: NodeRole,
+    shard_id: String,
+    credentials: NodeCredentials,
+}
+
+/// Per-node auth state. Applied via AUTH/SELECT to every connection the pool opens, so
+/// that a brand-new socket created after a failover or a marked-unhealthy reconnect
+/// doesn't surface NOAUTH on its first command.
+#[derive(Clone, Debug, Default)]
+pub struct NodeCredentials {
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub db: Option<i64>,
+}
+
+impl NodeCredentials {
+    async fn apply(&self, conn: &mut MultiplexedConnection) -> RedisResult<()> {
+        if let Some(password) = &self.password {
+            let mut auth_cmd = cmd("AUTH");
+            if let Some(username) = &self.username {
+                auth_cmd.arg(username);
+            }
+            auth_cmd.arg(password);
+            auth_cmd.query_async::<()>(conn).await?;
+        }
+        if let Some(db) = self.db {
+            cmd("SELECT").arg(db).query_async::<()>(conn).await?;
+        }
+        Ok(())
+    }
+}
 
 /// Handle to a dedicated connection set
 #[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
@@ -15,22 +66,64 @@ impl ConnectionHandle {
     }
 }
 
+/// Tunables for [`ConnectionPool`]'s per-node connection pools.
+#[derive(Clone, Copy, Debug)]
+pub struct ConnectionPoolConfig {
+    /// Maximum number of connections (idle + in-use) kept open to a single node.
+    pub max_per_node: usize,
+    /// How long a connection may sit idle in `available` before the reaper drops it.
+    pub idle_timeout: Duration,
+    /// How long a connection may live, idle or not, before the reaper drops it.
+    pub max_lifetime: Duration,
+    /// How long `acquire` waits for a free permit before giving up.
+    pub acquire_timeout: Duration,
+}
+
+impl Default for ConnectionPoolConfig {
+    fn default() -> Self {
+        Self {
+            max_per_node: 50,
+            idle_timeout: Duration::from_secs(5 * 60),
+            max_lifetime: Duration::from_secs(60 * 60),
+            acquire_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
 /// Connection state tracking
 struct ManagedConnection {
     conn: MultiplexedConnection,
     node_id: String,
     is_healthy: bool,
+    created_at: Instant,
+    last_used: Instant,
+    // Set once `get_pubsub` converts this connection into subscription mode. A
+    // subscribed connection can't serve ordinary request/response commands, so
+    // `release_dedicated` tears it down instead of returning it to `available`.
+    pinned_to_pubsub: bool,
+    // Held for the lifetime of this connection; dropping it returns the slot to the
+    // node's semaphore so a fresh `acquire` can open a new socket.
+    _permit: tokio::sync::OwnedSemaphorePermit,
 }
 
 impl ManagedConnection {
-    fn new(conn: MultiplexedConnection, node_id: String) -> Self {
+    fn new(
+        conn: MultiplexedConnection,
+        node_id: String,
+        permit: tokio::sync::OwnedSemaphorePermit,
+    ) -> Self {
+        let now = Instant::now();
         Self {
             conn,
             node_id,
             is_healthy: true,
+            created_at: now,
+            last_used: now,
+            pinned_to_pubsub: false,
+            _permit: permit,
         }
     }
-    
+
     fn mark_unhealthy(&mut self) {
         self.is_healthy = false;
     }
@@ -40,38 +133,73 @@ impl ManagedConnection {
 struct NodeConnectionPool {
     available: Vec<ManagedConnection>,
     in_use: usize,
+    // Bounds `available.len() + in_use` to `max_per_node`.
+    semaphore: Arc<Semaphore>,
 }
 
 impl NodeConnectionPool {
-    fn new() -> Self {
+    fn new(max_per_node: usize) -> Self {
         Self {
             available: Vec::new(),
             in_use: 0,
+            semaphore: Arc::new(Semaphore::new(max_per_node)),
         }
     }
 
-    async fn acquire(&mut self, client: &RedisClient, node_id: &str) -> RedisResult<ManagedConnection> {
-        // Try to reuse healthy connection
+    async fn acquire(
+        &mut self,
+        client: &RedisClient,
+        node_id: &str,
+        config: &ConnectionPoolConfig,
+        credentials: &NodeCredentials,
+    ) -> RedisResult<ManagedConnection> {
+        // Try to reuse a healthy, not-yet-expired connection.
         while let Some(mut managed) = self.available.pop() {
-            if managed.is_healthy {
+            if managed.is_healthy && managed.created_at.elapsed() < config.max_lifetime {
+                managed.last_used = Instant::now();
                 self.in_use += 1;
                 return Ok(managed);
             }
-            // Discard unhealthy connection
+            // Discard unhealthy or expired connection; its permit is released on drop.
         }
-        
-        // Create new connection
+
+        // Wait for a free slot before opening a new socket, so a burst of callers can't
+        // exceed `max_per_node` connections to this node.
+        let permit = tokio::time::timeout(
+            config.acquire_timeout,
+            Arc::clone(&self.semaphore).acquire_owned(),
+        )
+        .await
+        .map_err(|_| {
+            RedisError::from((
+                ErrorKind::IoError,
+                "Timed out waiting for an available connection slot",
+            ))
+        })?
+        .map_err(|_| RedisError::from((ErrorKind::ClientError, "Connection pool is shut down")))?;
+
         self.in_use += 1;
-        let conn = client.get_multiplexed_async_connection().await?;
-        Ok(ManagedConnection::new(conn, node_id.to_string()))
+        let mut conn = client.get_multiplexed_async_connection().await?;
+        // A brand-new connection is unauthenticated even if the client was constructed
+        // with credentials, so reapply them here rather than only at client construction.
+        credentials.apply(&mut conn).await?;
+        Ok(ManagedConnection::new(conn, node_id.to_string(), permit))
     }
 
-    fn release(&mut self, conn: ManagedConnection) {
+    fn release(&mut self, mut conn: ManagedConnection) {
         self.in_use = self.in_use.saturating_sub(1);
         if conn.is_healthy {
+            conn.last_used = Instant::now();
             self.available.push(conn);
         }
-        // Unhealthy connections are dropped
+        // Unhealthy connections are dropped, releasing their permit.
+    }
+
+    /// Drop idle connections that have exceeded `idle_timeout` or `max_lifetime`.
+    fn reap_idle(&mut self, idle_timeout: Duration, max_lifetime: Duration) {
+        self.available.retain(|managed| {
+            managed.last_used.elapsed() < idle_timeout && managed.created_at.elapsed() < max_lifetime
+        });
     }
 }
 
@@ -81,27 +209,145 @@ pub struct ConnectionPool {
     dedicated_sets: Arc<RwLock<HashMap<ConnectionHandle, HashMap<String, ManagedConnection>>>>,
     // Pool of available connections per node
     pools: Arc<RwLock<HashMap<String, NodeConnectionPool>>>,
-    // Redis clients per node for creating connections
-    clients: Arc<RwLock<HashMap<String, RedisClient>>>,
-}
-    dedicated_sets: Arc<RwLock<HashMap<ConnectionHandle, HashMap<String, MultiplexedConnection>>>>,
-    // Pool of available connections per node
-    pools: Arc<RwLock<HashMap<String, NodeConnectionPool>>>,
+    // Registered nodes, keyed by node id, for creating connections
+    nodes: Arc<RwLock<HashMap<String, NodeInfo>>>,
+    config: ConnectionPoolConfig,
+    // Round-robins reads across a shard's replicas
+    read_rr: AtomicUsize,
 }
 
 impl ConnectionPool {
     pub fn new() -> Self {
-        Self {
+        Self::with_config(ConnectionPoolConfig::default())
+    }
+
+    pub fn with_config(config: ConnectionPoolConfig) -> Self {
+        let pool = Self {
             dedicated_sets: Arc::new(RwLock::new(HashMap::new())),
             pools: Arc::new(RwLock::new(HashMap::new())),
-            clients: Arc::new(RwLock::new(HashMap::new())),
-        }
+            nodes: Arc::new(RwLock::new(HashMap::new())),
+            config,
+            read_rr: AtomicUsize::new(0),
+        };
+        pool.spawn_reaper();
+        pool
+    }
+
+    /// Periodically sweeps every node's idle connections, evicting ones that have been
+    /// idle longer than `idle_timeout` or alive longer than `max_lifetime`.
+    fn spawn_reaper(&self) {
+        let pools = Arc::clone(&self.pools);
+        let idle_timeout = self.config.idle_timeout;
+        let max_lifetime = self.config.max_lifetime;
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(idle_timeout.max(Duration::from_secs(1)));
+            loop {
+                ticker.tick().await;
+                let mut pools = pools.write().await;
+                for pool in pools.values_mut() {
+                    pool.reap_idle(idle_timeout, max_lifetime);
+                }
+            }
+        });
     }
 
-    /// Register a Redis client for a node
+    /// Register a Redis client for a node, treating it as the primary of its own
+    /// single-node shard with no credentials to reapply on reconnect. Prefer
+    /// [`Self::register_node_with_role`] for replicated setups, or
+    /// [`Self::register_node_full`] for password/ACL-protected ones.
     pub async fn register_node(&self, node_id: String, client: RedisClient) {
-        let mut clients = self.clients.write().await;
-        clients.insert(node_id, client);
+        let shard_id = node_id.clone();
+        self.register_node_with_role(node_id, client, NodeRole::Primary, shard_id)
+            .await;
+    }
+
+    /// Register a Redis client for a node that belongs to `shard_id`, recording whether it
+    /// is currently the shard's primary or one of its replicas.
+    pub async fn register_node_with_role(
+        &self,
+        node_id: String,
+        client: RedisClient,
+        role: NodeRole,
+        shard_id: String,
+    ) {
+        self.register_node_full(node_id, client, role, shard_id, NodeCredentials::default())
+            .await;
+    }
+
+    /// Register a Redis client for a node, recording its role, shard, and the
+    /// credentials the pool should reapply to every connection it opens for this node
+    /// (including ones opened after a failover or a marked-unhealthy reconnect).
+    pub async fn register_node_full(
+        &self,
+        node_id: String,
+        client: RedisClient,
+        role: NodeRole,
+        shard_id: String,
+        credentials: NodeCredentials,
+    ) {
+        let mut nodes = self.nodes.write().await;
+        nodes.insert(
+            node_id,
+            NodeInfo {
+                client,
+                role,
+                shard_id,
+                credentials,
+            },
+        );
+    }
+
+    pub(crate) async fn find_primary(&self, shard_id: &str) -> RedisResult<String> {
+        let nodes = self.nodes.read().await;
+        nodes
+            .iter()
+            .find(|(_, info)| info.shard_id == shard_id && info.role == NodeRole::Primary)
+            .map(|(node_id, _)| node_id.clone())
+            .ok_or_else(|| {
+                RedisError::from((
+                    ErrorKind::ClientError,
+                    "No primary registered for shard",
+                ))
+            })
+    }
+
+    /// Picks a node to read from for `shard_id`, load-balancing across its registered
+    /// replicas and falling back to the primary if the shard has none.
+    pub(crate) async fn pick_read_node(&self, shard_id: &str) -> RedisResult<String> {
+        let nodes = self.nodes.read().await;
+        let mut replicas: Vec<&String> = nodes
+            .iter()
+            .filter(|(_, info)| info.shard_id == shard_id && info.role == NodeRole::Replica)
+            .map(|(node_id, _)| node_id)
+            .collect();
+        if replicas.is_empty() {
+            drop(nodes);
+            return self.find_primary(shard_id).await;
+        }
+        replicas.sort();
+        let index = self.read_rr.fetch_add(1, Ordering::Relaxed) % replicas.len();
+        Ok(replicas[index].clone())
+    }
+
+    /// Acquire a dedicated connection to `shard_id`'s primary, for writes.
+    pub async fn get_write_connection(
+        &self,
+        handle: ConnectionHandle,
+        shard_id: &str,
+    ) -> RedisResult<MultiplexedConnection> {
+        let node_id = self.find_primary(shard_id).await?;
+        self.get_connection(handle, &node_id).await
+    }
+
+    /// Acquire a dedicated connection to one of `shard_id`'s replicas (or its primary, if
+    /// the shard has no replicas), for reads.
+    pub async fn get_read_connection(
+        &self,
+        handle: ConnectionHandle,
+        shard_id: &str,
+    ) -> RedisResult<MultiplexedConnection> {
+        let node_id = self.pick_read_node(shard_id).await?;
+        self.get_connection(handle, &node_id).await
     }
 
     /// Acquire a dedicated connection handle
@@ -119,37 +365,74 @@ impl ConnectionPool {
         node_id: &str,
     ) -> RedisResult<MultiplexedConnection> {
         let mut sets = self.dedicated_sets.write().await;
-        let node_conns = sets.get_mut(&handle)
+        let node_conns = sets
+            .get_mut(&handle)
             .ok_or_else(|| RedisError::from((ErrorKind::ClientError, "Invalid handle")))?;
 
         // Check if we already have a connection to this node
-        if let Some(managed) = node_conns.get(node_id) {
+        if let Some(managed) = node_conns.get_mut(node_id) {
             if managed.is_healthy {
+                managed.last_used = Instant::now();
                 return Ok(managed.conn.clone());
             }
             // Connection is unhealthy, will recreate below
         }
 
         // Get or create connection from pool
-        let clients = self.clients.read().await;
-        let client = clients.get(node_id)
+        let nodes = self.nodes.read().await;
+        let node = nodes
+            .get(node_id)
             .ok_or_else(|| RedisError::from((ErrorKind::ClientError, "Node not found")))?;
 
         let mut pools = self.pools.write().await;
-        let pool = pools.entry(node_id.to_string()).or_insert_with(NodeConnectionPool::new);
-        let managed = pool.acquire(client, node_id).await?;
-        
+        let pool = pools
+            .entry(node_id.to_string())
+            .or_insert_with(|| NodeConnectionPool::new(self.config.max_per_node));
+        let managed = pool
+            .acquire(&node.client, node_id, &self.config, &node.credentials)
+            .await?;
+
         let conn = managed.conn.clone();
         node_conns.insert(node_id.to_string(), managed);
         Ok(conn)
     }
 
+    /// Converts `handle`'s connection to `node_id` into pub/sub push-message mode and
+    /// returns a stream of incoming messages. The connection can no longer serve
+    /// ordinary request/response commands afterward, so it's marked pinned and torn
+    /// down (rather than pooled) once the handle is released.
+    pub async fn get_pubsub(
+        &self,
+        handle: ConnectionHandle,
+        node_id: &str,
+    ) -> RedisResult<mpsc::Receiver<Msg>> {
+        let mut conn = self.get_connection(handle, node_id).await?;
+        let (tx, rx) = mpsc::channel(64);
+        conn.set_push_sender(tx);
+
+        let mut sets = self.dedicated_sets.write().await;
+        if let Some(node_conns) = sets.get_mut(&handle) {
+            if let Some(managed) = node_conns.get_mut(node_id) {
+                managed.pinned_to_pubsub = true;
+            }
+        }
+        Ok(rx)
+    }
+
     /// Release all connections associated with a handle
     pub async fn release_dedicated(&self, handle: ConnectionHandle) {
         let mut sets = self.dedicated_sets.write().await;
         if let Some(node_conns) = sets.remove(&handle) {
             let mut pools = self.pools.write().await;
             for (node_id, managed) in node_conns {
+                if managed.pinned_to_pubsub {
+                    // A subscribed connection can't be reused for ordinary commands;
+                    // drop it (and its semaphore permit) instead of pooling it.
+                    if let Some(pool) = pools.get_mut(&node_id) {
+                        pool.in_use = pool.in_use.saturating_sub(1);
+                    }
+                    continue;
+                }
                 if let Some(pool) = pools.get_mut(&node_id) {
                     pool.release(managed);
                 }
@@ -157,17 +440,26 @@ impl ConnectionPool {
         }
     }
 
-    /// Handle failover: mark old node connections as unhealthy and remap to new node
+    /// Handle failover: mark old node connections as unhealthy, remap to new node, and
+    /// promote the new node to primary so subsequent writes follow it.
     pub async fn handle_failover(&self, old_node_id: &str, new_node_id: &str) {
         let mut sets = self.dedicated_sets.write().await;
-        
-        // Mark all connections to old node as unhealthy
+
+        // Mark all connections to old node as unhealthy. A pinned pub/sub connection
+        // is removed outright instead, so its channel closes immediately and the
+        // caller knows to resubscribe rather than discovering it on next use.
         for (_handle, node_conns) in sets.iter_mut() {
-            if let Some(managed) = node_conns.get_mut(old_node_id) {
+            let is_pubsub = node_conns
+                .get(old_node_id)
+                .map(|managed| managed.pinned_to_pubsub)
+                .unwrap_or(false);
+            if is_pubsub {
+                node_conns.remove(old_node_id);
+            } else if let Some(managed) = node_conns.get_mut(old_node_id) {
                 managed.mark_unhealthy();
             }
         }
-        
+
         // Clean up pool for old node
         let mut pools = self.pools.write().await;
         if let Some(mut old_pool) = pools.remove(old_node_id) {
@@ -176,6 +468,28 @@ impl ConnectionPool {
                 managed.mark_unhealthy();
             }
         }
+
+        // The promoted replica takes over as primary of its shard, and the old
+        // primary is demoted so `find_primary` can't still return it afterward.
+        let mut nodes = self.nodes.write().await;
+        if let Some(info) = nodes.get_mut(old_node_id) {
+            info.role = NodeRole::Replica;
+        }
+        if let Some(info) = nodes.get_mut(new_node_id) {
+            info.role = NodeRole::Primary;
+        }
+    }
+
+    /// Number of connections currently sitting idle in `node_id`'s pool. Exposed so
+    /// tests can observe `spawn_reaper`'s effect directly - `get_connection` reusing
+    /// successfully doesn't, on its own, say whether a stale entry was actually reaped.
+    pub(crate) async fn available_count(&self, node_id: &str) -> usize {
+        self.pools
+            .read()
+            .await
+            .get(node_id)
+            .map(|pool| pool.available.len())
+            .unwrap_or(0)
     }
 
     /// Handle reconnection: mark connection as unhealthy, will be recreated on next use
@@ -191,9 +505,9 @@ impl ConnectionPool {
     /// Handle topology change: remove connections to nodes no longer in cluster
     pub async fn handle_topology_change(&self, active_nodes: &[String]) {
         let active_set: std::collections::HashSet<_> = active_nodes.iter().collect();
-        
+
         let mut sets = self.dedicated_sets.write().await;
-        
+
         // Remove connections to nodes no longer in cluster
         for (_handle, node_conns) in sets.iter_mut() {
             node_conns.retain(|node_id, managed| {
@@ -205,7 +519,7 @@ impl ConnectionPool {
                 should_keep
             });
         }
-        
+
         // Clean up pools for removed nodes
         let mut pools = self.pools.write().await;
         pools.retain(|node_id, _| active_set.contains(&node_id));
@@ -3,29 +3,179 @@
 use bytes::BytesMut;
 use logger_core::log_debug;
 use once_cell::sync::Lazy;
+use redis::{ErrorKind, RedisError, RedisResult};
 use sha1_smol::Sha1;
 use std::{collections::HashMap, sync::Arc};
-use tokio::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, RwLock};
+
+/// A dedicated current-thread runtime for the blocking facade (`add_script`,
+/// `get_script`, `remove_script`), used only when the calling thread has no ambient
+/// Tokio runtime of its own. Lets foreign-language bindings that drive this crate from
+/// their own native threads call these functions without first having to stand up a
+/// runtime themselves.
+static BLOCKING_RUNTIME: Lazy<tokio::runtime::Runtime> = Lazy::new(|| {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("Failed to build the scripts_container blocking runtime")
+});
+
+/// Runs `fut` to completion from a sync context: reuses the ambient runtime if the
+/// calling thread is on one, otherwise falls back to `BLOCKING_RUNTIME`. Calling this
+/// from a worker thread of a current-thread runtime would deadlock (it would have to
+/// block on itself to make progress), so that case returns a clear error instead of
+/// panicking or hanging.
+fn run_blocking<F: std::future::Future>(fut: F) -> RedisResult<F::Output> {
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) => match handle.runtime_flavor() {
+            tokio::runtime::RuntimeFlavor::CurrentThread => Err(RedisError::from((
+                ErrorKind::ClientError,
+                "Cannot call a blocking scripts_container API from within a current-thread Tokio runtime",
+            ))),
+            _ => Ok(tokio::task::block_in_place(|| handle.block_on(fut))),
+        },
+        Err(_) => Ok(BLOCKING_RUNTIME.block_on(fut)),
+    }
+}
 
 struct ScriptEntry {
     code: Arc<BytesMut>,
     ref_count: usize,
+    last_access: Instant,
+}
+
+/// `get_script_async` is on the hot path of every EVALSHA-style dispatch and vastly
+/// outnumbers `add`/`remove` once scripts are registered. A single global lock would
+/// serialize all of those lookups against each other and against writes, so the
+/// container is split into independently-locked shards keyed by the first byte of the
+/// hash: concurrent lookups that land in different shards never contend.
+const SHARD_COUNT: usize = 16;
+
+struct Shard {
+    entries: Mutex<HashMap<String, ScriptEntry>>,
+}
+
+static SHARDS: Lazy<Vec<Shard>> = Lazy::new(|| {
+    (0..SHARD_COUNT)
+        .map(|_| Shard {
+            entries: Mutex::new(HashMap::new()),
+        })
+        .collect()
+});
+
+/// Decodes `hash`'s first hex digit into a shard index. Split out from `shard_for` so
+/// the distribution across `SHARD_COUNT` shards can be tested without needing to find
+/// inputs that hash to particular SHA1 digests.
+pub(crate) fn shard_index_for(hash: &str) -> usize {
+    // `hash` is a SHA1 hex digest, so its first character is a hex digit, not a raw
+    // byte - decoding its ASCII value directly (e.g. `b'a' % 16 == 1`) would collide
+    // digits and letters onto the same shards and leave others permanently empty.
+    hash.get(..1)
+        .and_then(|c| u8::from_str_radix(c, 16).ok())
+        .unwrap_or(0) as usize
+        % SHARD_COUNT
+}
+
+fn shard_for(hash: &str) -> &'static Shard {
+    &SHARDS[shard_index_for(hash)]
+}
+
+/// Tunables for the scripts container's memory/idle-time eviction. By default both are
+/// `None`, which preserves the original behavior: no byte cap, and an entry is removed
+/// as soon as its `ref_count` drops to zero.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ScriptsContainerConfig {
+    /// Total bytes of script code (sum of `code.len()`) allowed across all entries,
+    /// split evenly across the container's shards. Once a newly-added script pushes
+    /// its shard over its share of the budget, least-recently-used entries in that
+    /// shard with `ref_count == 0` are evicted until back under budget. `None` means
+    /// unbounded.
+    pub byte_budget: Option<usize>,
+    /// How long a `ref_count == 0` entry may sit idle before it becomes eligible for
+    /// eviction. `None` disables idle expiry; if `byte_budget` is also `None`, a script
+    /// is dropped the instant its ref count reaches zero, as before, and otherwise it
+    /// stays cached until byte-budget eviction removes it.
+    pub idle_ttl: Option<Duration>,
+}
+
+static CONFIG: Lazy<RwLock<ScriptsContainerConfig>> =
+    Lazy::new(|| RwLock::new(ScriptsContainerConfig::default()));
+
+/// Override the scripts container's byte budget / idle TTL.
+pub async fn configure(config: ScriptsContainerConfig) {
+    *CONFIG.write().await = config;
+}
+
+/// Scripts larger than this are hashed on the blocking pool instead of inline, so a
+/// multi-hundred-KB script doesn't stall the calling task's executor thread.
+const BLOCKING_HASH_THRESHOLD_BYTES: usize = 16 * 1024;
+
+async fn hash_script(script: &[u8]) -> String {
+    if script.len() > BLOCKING_HASH_THRESHOLD_BYTES {
+        let owned = script.to_vec();
+        tokio::task::spawn_blocking(move || Sha1::from(owned.as_slice()).digest().to_string())
+            .await
+            .expect("SHA1 hashing task panicked")
+    } else {
+        Sha1::from(script).digest().to_string()
+    }
+}
+
+fn total_bytes(container: &HashMap<String, ScriptEntry>) -> usize {
+    container.values().map(|entry| entry.code.len()).sum()
+}
+
+/// Drops `ref_count == 0` entries that have been idle longer than `idle_ttl`. A no-op
+/// when no TTL is configured.
+fn evict_expired(container: &mut HashMap<String, ScriptEntry>, idle_ttl: Option<Duration>) {
+    let Some(ttl) = idle_ttl else { return };
+    container.retain(|_, entry| entry.ref_count > 0 || entry.last_access.elapsed() < ttl);
 }
 
-static CONTAINER: Lazy<Mutex<HashMap<String, ScriptEntry>>> =
-    Lazy::new(|| Mutex::new(HashMap::new()));
+/// Evicts least-recently-used `ref_count == 0` entries, oldest first, until the total
+/// byte count is back under `byte_budget`. Pinned (`ref_count > 0`) entries are never
+/// touched, so this may leave the container over budget if everything left is pinned.
+fn evict_lru_until_under_budget(container: &mut HashMap<String, ScriptEntry>, byte_budget: Option<usize>) {
+    let Some(budget) = byte_budget else { return };
+    while total_bytes(container) > budget {
+        let oldest_unpinned = container
+            .iter()
+            .filter(|(_, entry)| entry.ref_count == 0)
+            .min_by_key(|(_, entry)| entry.last_access)
+            .map(|(hash, _)| hash.clone());
+        match oldest_unpinned {
+            Some(hash) => {
+                container.remove(&hash);
+            }
+            None => break,
+        }
+    }
+}
 
 // Internal async implementation - use this from async contexts
 pub async fn add_script_async(script: &[u8]) -> String {
-    let hash = Sha1::from(script).digest().to_string();
-    let mut container = CONTAINER.lock().await;
+    let hash = hash_script(script).await;
+    let config = *CONFIG.read().await;
+    let per_shard_budget = config.byte_budget.map(|budget| budget / SHARD_COUNT);
+    let mut container = shard_for(&hash).entries.lock().await;
+
+    evict_expired(&mut container, config.idle_ttl);
+
     container
         .entry(hash.clone())
-        .and_modify(|entry| entry.ref_count += 1)
+        .and_modify(|entry| {
+            entry.ref_count += 1;
+            entry.last_access = Instant::now();
+        })
         .or_insert_with(|| ScriptEntry {
             code: Arc::new(BytesMut::from(script)),
             ref_count: 1,
+            last_access: Instant::now(),
         });
+
+    evict_lru_until_under_budget(&mut container, per_shard_budget);
+
     log_debug(
         "scripts_container add",
         format!("Added script with hash: `{:?}`", hash),
@@ -34,35 +184,62 @@ pub async fn add_script_async(script: &[u8]) -> String {
 }
 
 // Public blocking API - use this from sync contexts only
-pub fn add_script(script: &[u8]) -> String {
-    tokio::runtime::Handle::current().block_on(add_script_async(script))
+//
+// BREAKING CHANGE: this used to return a plain `String`. Giving the blocking facade its
+// own fallback runtime (see `run_blocking`) introduced a case - calling this from a
+// current-thread runtime's worker thread - that must be reported rather than silently
+// deadlocking, so the return type grew a `RedisResult` wrapper. Any caller outside this
+// crate (language-binding FFI layers included) needs to be updated to unwrap/propagate
+// the new `Err` case.
+pub fn add_script(script: &[u8]) -> RedisResult<String> {
+    run_blocking(add_script_async(script))
 }
 
 // Internal async implementation - use this from async contexts
 pub async fn get_script_async(hash: &str) -> Option<Arc<BytesMut>> {
-    CONTAINER.lock().await.get(hash).map(|entry| entry.code.clone())
+    let idle_ttl = CONFIG.read().await.idle_ttl;
+    let mut container = shard_for(hash).entries.lock().await;
+    evict_expired(&mut container, idle_ttl);
+    container.get_mut(hash).map(|entry| {
+        entry.last_access = Instant::now();
+        entry.code.clone()
+    })
 }
 
 // Public blocking API - use this from sync contexts only
-pub fn get_script(hash: &str) -> Option<Arc<BytesMut>> {
-    tokio::runtime::Handle::current().block_on(get_script_async(hash))
+//
+// BREAKING CHANGE: this used to return a plain `Option<Arc<BytesMut>>`. See the
+// `BREAKING CHANGE` note on `add_script` - same reason, same caller-visible change.
+pub fn get_script(hash: &str) -> RedisResult<Option<Arc<BytesMut>>> {
+    run_blocking(get_script_async(hash))
 }
 
 pub async fn remove_script_async(hash: &str) {
-    let mut container = CONTAINER.lock().await;
+    let config = *CONFIG.read().await;
+    let mut container = shard_for(hash).entries.lock().await;
     if let Some(entry) = container.get_mut(hash) {
-        entry.ref_count -= 1;
+        entry.ref_count = entry.ref_count.saturating_sub(1);
         if entry.ref_count == 0 {
-            container.remove(hash);
-            log_debug(
-                "scripts_container remove",
-                format!("Removed script with hash: `{:?}`", hash),
-            );
+            // With no eviction policy configured, preserve the original behavior of
+            // dropping the script the instant it's no longer referenced. Otherwise,
+            // leave it cached - it's now eligible for idle-TTL or byte-budget eviction.
+            if config.idle_ttl.is_none() && config.byte_budget.is_none() {
+                container.remove(hash);
+                log_debug(
+                    "scripts_container remove",
+                    format!("Removed script with hash: `{:?}`", hash),
+                );
+            } else {
+                entry.last_access = Instant::now();
+            }
         }
     }
 }
 
 // Public blocking API - use this from sync contexts only
-pub fn remove_script(hash: &str) {
-    tokio::runtime::Handle::current().block_on(remove_script_async(hash))
+//
+// BREAKING CHANGE: this used to return `()`. See the `BREAKING CHANGE` note on
+// `add_script` - same reason, same caller-visible change.
+pub fn remove_script(hash: &str) -> RedisResult<()> {
+    run_blocking(remove_script_async(hash))
 }
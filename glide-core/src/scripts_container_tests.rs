@@ -0,0 +1,112 @@
+use crate::scripts_container::{
+    add_script, add_script_async, configure, get_script, get_script_async, remove_script,
+    remove_script_async, shard_index_for, ScriptsContainerConfig,
+};
+use once_cell::sync::Lazy;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+/// `configure()` mutates process-wide state shared by every test in this binary, and
+/// the default test runner executes tests concurrently on separate threads. Tests that
+/// call `configure()` take this guard for their whole run so they can't stomp each
+/// other's config mid-sleep; there's no `serial_test` (or similar) dependency in this
+/// repo to reach for instead.
+static CONFIG_TEST_GUARD: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+#[test]
+fn test_shard_index_covers_every_shard_for_hex_digests() {
+    // Every hex digit must land in its own shard - the bug this guards against mapped
+    // 'a'..'f' onto shards 1..6 (colliding with '1'..'6') via their raw ASCII value
+    // instead of their decoded nibble.
+    let digits = "0123456789abcdef";
+    let indices: Vec<usize> = digits
+        .chars()
+        .map(|c| shard_index_for(&c.to_string()))
+        .collect();
+    assert_eq!(indices, (0..16).collect::<Vec<usize>>());
+}
+
+#[test]
+fn test_shard_index_is_case_insensitive() {
+    assert_eq!(shard_index_for("a1b2"), shard_index_for("A1B2"));
+}
+
+#[tokio::test]
+async fn test_large_script_is_hashed_on_the_blocking_pool() {
+    // Comfortably over `BLOCKING_HASH_THRESHOLD_BYTES`, so this exercises the
+    // `spawn_blocking` path rather than the inline `Sha1::digest` one.
+    let script = vec![b'x'; 32 * 1024];
+    let hash = add_script_async(&script).await;
+
+    assert_eq!(hash.len(), 40); // SHA1 hex digest
+    let stored = get_script_async(&hash).await.unwrap();
+    assert_eq!(&stored[..], script.as_slice());
+
+    remove_script_async(&hash).await;
+}
+
+#[tokio::test]
+async fn test_idle_ttl_expires_unreferenced_script_on_access() {
+    let _guard = CONFIG_TEST_GUARD.lock().await;
+    configure(ScriptsContainerConfig {
+        byte_budget: None,
+        idle_ttl: Some(Duration::from_millis(20)),
+    })
+    .await;
+
+    let hash = add_script_async(b"return 1").await;
+    remove_script_async(&hash).await; // ref_count -> 0, but lingers for idle_ttl
+
+    tokio::time::sleep(Duration::from_millis(60)).await;
+    assert!(get_script_async(&hash).await.is_none());
+
+    configure(ScriptsContainerConfig::default()).await;
+}
+
+#[tokio::test]
+async fn test_byte_budget_evicts_unreferenced_entries_under_pressure() {
+    let _guard = CONFIG_TEST_GUARD.lock().await;
+    configure(ScriptsContainerConfig {
+        byte_budget: Some(16), // 1 byte per shard - any script at all is over budget
+        idle_ttl: None,
+    })
+    .await;
+
+    let mut hashes = Vec::new();
+    for i in 0..64 {
+        let hash = add_script_async(format!("return {i}").as_bytes()).await;
+        remove_script_async(&hash).await; // ref_count -> 0, eligible for LRU eviction
+        hashes.push(hash);
+    }
+
+    // With 64 entries spread across 16 shards and a budget that's immediately
+    // exceeded by a single entry, every shard must have evicted down to at most its
+    // most-recently-added entry - strictly fewer than all 64 should still be cached.
+    let mut survivors = 0;
+    for hash in &hashes {
+        if get_script_async(hash).await.is_some() {
+            survivors += 1;
+        }
+    }
+    assert!(survivors < hashes.len());
+
+    configure(ScriptsContainerConfig::default()).await;
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn test_blocking_api_refuses_to_deadlock_on_a_current_thread_runtime() {
+    let err = add_script(b"return 1").unwrap_err();
+    assert!(err.to_string().contains("current-thread"));
+}
+
+#[test]
+fn test_blocking_api_falls_back_to_its_own_runtime_without_an_ambient_one() {
+    // No Tokio runtime on this thread at all, so `run_blocking` must fall back to
+    // `BLOCKING_RUNTIME` rather than panicking for lack of a `Handle::current()`.
+    let hash = std::thread::spawn(|| add_script(b"return 2").unwrap())
+        .join()
+        .unwrap();
+
+    assert!(get_script(&hash).unwrap().is_some());
+    remove_script(&hash).unwrap();
+}